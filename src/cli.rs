@@ -17,20 +17,29 @@ pub struct Cli {
 pub enum Commands {
     /// Encrypt a file
     Encrypt {
-        /// Input file to encrypt
+        /// Input file to encrypt. Use "-" to read from stdin
         #[arg(value_name = "FILE")]
         input: PathBuf,
-        
-        /// Output file path
+
+        /// Output file path. Use "-" to write to stdout
         #[arg(short, long, value_name = "FILE")]
         output: PathBuf,
         
-        /// Encryption algorithm to use
-        #[arg(short, long, default_value = "aes256gcm")]
+        /// Encryption algorithm to use. Not applicable with --recipient,
+        /// which always uses XChaCha20-Poly1305
+        #[arg(short, long, default_value = "aes256gcm", conflicts_with = "recipient")]
         algorithm: Algorithm,
-        
-        /// KDF iterations (higher = more secure but slower)
-        #[arg(short, long, default_value = "3")]
+
+        /// KDF iterations, 1-100 (higher = more secure but slower; decryption
+        /// refuses a header above 100). Not applicable with --recipient,
+        /// which has no password to derive a key from
+        #[arg(
+            short,
+            long,
+            default_value = "3",
+            value_parser = clap::value_parser!(u32).range(1..=100),
+            conflicts_with = "recipient"
+        )]
         iterations: u32,
         
         /// Force overwrite if output file exists
@@ -40,24 +49,70 @@ pub enum Commands {
         /// Verbose output
         #[arg(short, long)]
         verbose: bool,
+
+        /// Save the password under this name in the OS keyring (Secret
+        /// Service/Keychain/Credential Manager) for later use with --key
+        #[arg(long, value_name = "NAME")]
+        save_key: Option<String>,
+
+        /// Encrypt to this X25519 recipient public key (hex-encoded, from
+        /// `keygen`) instead of a password
+        #[arg(long, value_name = "PUBLIC_KEY_HEX", conflicts_with_all = ["save_key"])]
+        recipient: Option<String>,
     },
-    
+
     /// Decrypt a file
     Decrypt {
-        /// Input file to decrypt
+        /// Input file to decrypt. Use "-" to read from stdin
         #[arg(value_name = "FILE")]
         input: PathBuf,
-        
-        /// Output file path
+
+        /// Output file path. Use "-" to write to stdout
         #[arg(short, long, value_name = "FILE")]
         output: PathBuf,
-        
+
         /// Force overwrite if output file exists
         #[arg(short, long)]
         force: bool,
-        
+
         /// Verbose output
         #[arg(short, long)]
         verbose: bool,
+
+        /// Fetch the password from the OS keyring entry saved under this
+        /// name via --save-key, instead of VAULT_PASSWORD or a prompt
+        #[arg(long, value_name = "NAME")]
+        key: Option<String>,
+
+        /// Decrypt a recipient-mode file using this X25519 secret key
+        /// (hex-encoded, from `keygen`) instead of a password
+        #[arg(long, value_name = "SECRET_KEY_HEX", conflicts_with_all = ["key"])]
+        identity: Option<String>,
+    },
+
+    /// Check that an encrypted file still authenticates under a password,
+    /// without writing any plaintext to disk
+    Verify {
+        /// Encrypted file to verify. Use "-" to read from stdin
+        #[arg(value_name = "FILE")]
+        input: PathBuf,
+
+        /// Fetch the password from the OS keyring entry saved under this
+        /// name via --save-key, instead of VAULT_PASSWORD or a prompt
+        #[arg(long, value_name = "NAME")]
+        key: Option<String>,
+    },
+
+    /// Generate a new X25519 keypair for recipient-mode encryption
+    Keygen {
+        /// Write the secret key (hex-encoded) to this file instead of the OS
+        /// keyring
+        #[arg(long, value_name = "FILE", conflicts_with = "save_key")]
+        secret_out: Option<PathBuf>,
+
+        /// Save the secret key in the OS keyring under this name instead of
+        /// a file
+        #[arg(long, value_name = "NAME", conflicts_with = "secret_out")]
+        save_key: Option<String>,
     },
 }
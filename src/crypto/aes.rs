@@ -0,0 +1,310 @@
+use anyhow::Result;
+use rand::RngCore;
+use ring::aead::{Aad, BoundKey, Nonce, NonceSequence, OpeningKey, SealingKey, UnboundKey, AES_256_GCM};
+use ring::error::Unspecified;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use zeroize::Zeroizing;
+
+use super::kdf;
+use super::{
+    chunk_aad, DynAsyncRead, DynAsyncWrite, CHUNK_SIZE, SALT_SIZE, SENTINEL_LEN_MARKER, VERSION,
+};
+use crate::crypto::kdf::KdfParams;
+use crate::crypto::{Algorithm, Mode};
+use crate::error::VaultError;
+
+/// Per-chunk nonce sequence for AES-256-GCM: each 12-byte nonce is the
+/// random per-file prefix followed by the chunk counter, so nonces are
+/// unique and derived from the prefix actually stored in the header.
+struct CounterNonceSequence {
+    prefix: [u8; 4],
+    counter: u64,
+}
+
+impl CounterNonceSequence {
+    fn new(prefix: [u8; 4]) -> Self {
+        Self { prefix, counter: 0 }
+    }
+}
+
+impl NonceSequence for CounterNonceSequence {
+    fn advance(&mut self) -> Result<Nonce, Unspecified> {
+        let mut nonce_bytes = [0u8; 12];
+        nonce_bytes[..4].copy_from_slice(&self.prefix);
+        nonce_bytes[4..].copy_from_slice(&self.counter.to_le_bytes());
+        self.counter += 1;
+        Nonce::try_assume_unique_for_key(&nonce_bytes)
+    }
+}
+
+/// Encrypt a stream using AES-256-GCM
+pub async fn encrypt_file(
+    input: &mut DynAsyncRead,
+    output: &mut DynAsyncWrite,
+    password: &Zeroizing<String>,
+    iterations: u32,
+) -> Result<()> {
+    // Generate random salt
+    let mut salt = vec![0u8; SALT_SIZE];
+    rand::thread_rng().fill_bytes(&mut salt);
+
+    // Derive key from password
+    let params = KdfParams::new(iterations);
+    let key = kdf::derive_key(password, &salt, &params)?;
+
+    // Generate random 4-byte nonce prefix; each chunk's 12-byte nonce is
+    // `prefix || chunk_counter`
+    let mut nonce_prefix = [0u8; 4];
+    rand::thread_rng().fill_bytes(&mut nonce_prefix);
+
+    // Write header: version (1 byte) + mode (1 byte) + algorithm (1 byte) + salt + KDF params + nonce prefix
+    let mut header_bytes = Vec::with_capacity(3 + salt.len() + kdf::PARAMS_SIZE + nonce_prefix.len());
+    header_bytes.push(VERSION);
+    header_bytes.push(Mode::Password.to_byte());
+    header_bytes.push(Algorithm::Aes256Gcm.to_byte());
+    header_bytes.extend_from_slice(&salt);
+    header_bytes.extend_from_slice(&params.to_bytes());
+    header_bytes.extend_from_slice(&nonce_prefix);
+    output.write_all(&header_bytes).await?;
+
+    // Create sealing key
+    let unbound_key = UnboundKey::new(&AES_256_GCM, &key)
+        .map_err(|_| VaultError::EncryptionError("Failed to create key".to_string()))?;
+
+    let nonce_sequence = CounterNonceSequence::new(nonce_prefix);
+    let mut sealing_key = SealingKey::new(unbound_key, nonce_sequence);
+
+    // Encrypt the stream in chunks, binding the header and chunk index into
+    // each chunk's AAD
+    let mut buffer = vec![0u8; CHUNK_SIZE];
+    let mut index: u64 = 0;
+
+    loop {
+        let bytes_read = input.read(&mut buffer).await?;
+        if bytes_read == 0 {
+            break;
+        }
+
+        let mut in_out = buffer[..bytes_read].to_vec();
+        let aad = chunk_aad(&header_bytes, index, false);
+        index += 1;
+
+        sealing_key
+            .seal_in_place_append_tag(Aad::from(aad), &mut in_out)
+            .map_err(|_| VaultError::EncryptionError("Encryption failed".to_string()))?;
+
+        // Write chunk size (4 bytes) + encrypted chunk
+        output.write_u32(in_out.len() as u32).await?;
+        output.write_all(&in_out).await?;
+    }
+
+    // Append the truncation sentinel: a zero-length chunk authenticated under
+    // the true count of data chunks that preceded it (so it can't be spliced
+    // to an earlier point in the stream), flagged on disk with a `u32::MAX`
+    // length marker so it can't be confused with a real (and necessarily
+    // shorter) chunk.
+    let mut sentinel = Vec::new();
+    let sentinel_aad = chunk_aad(&header_bytes, index, true);
+    sealing_key
+        .seal_in_place_append_tag(Aad::from(sentinel_aad), &mut sentinel)
+        .map_err(|_| VaultError::EncryptionError("Encryption failed".to_string()))?;
+    output.write_u32(SENTINEL_LEN_MARKER).await?;
+    output.write_all(&sentinel).await?;
+
+    output.flush().await?;
+
+    Ok(())
+}
+
+/// Decrypt a stream using AES-256-GCM. `version` is the file format version
+/// already read (and validated) by the caller from the first header byte.
+pub async fn decrypt_file(
+    input: &mut DynAsyncRead,
+    output: &mut DynAsyncWrite,
+    password: &Zeroizing<String>,
+    version: u8,
+) -> Result<()> {
+    // Read salt
+    let mut salt = vec![0u8; SALT_SIZE];
+    input.read_exact(&mut salt).await?;
+
+    // Version 2+ stores the exact KDF params used at encryption time; version 1
+    // files hardcoded them, so fall back to those defaults.
+    let params = if version >= 2 {
+        let mut params_bytes = [0u8; kdf::PARAMS_SIZE];
+        input.read_exact(&mut params_bytes).await?;
+        KdfParams::from_bytes(&params_bytes)?
+    } else {
+        KdfParams::legacy_default()
+    };
+
+    // Read nonce prefix
+    let mut nonce_prefix = [0u8; 4];
+    input.read_exact(&mut nonce_prefix).await?;
+
+    // Recompute the exact header bytes so version 3+ chunks can be
+    // re-authenticated against the same AAD used at encryption time. Version
+    // 4+ headers carry a mode byte that versions 1-3 never wrote.
+    let mut header_bytes = Vec::with_capacity(3 + salt.len() + kdf::PARAMS_SIZE + nonce_prefix.len());
+    header_bytes.push(version);
+    if version >= 4 {
+        header_bytes.push(Mode::Password.to_byte());
+    }
+    header_bytes.push(Algorithm::Aes256Gcm.to_byte());
+    header_bytes.extend_from_slice(&salt);
+    header_bytes.extend_from_slice(&params.to_bytes());
+    header_bytes.extend_from_slice(&nonce_prefix);
+
+    // Derive key from password
+    let key = kdf::derive_key(password, &salt, &params)?;
+
+    // Create opening key
+    let unbound_key = UnboundKey::new(&AES_256_GCM, &key)
+        .map_err(|_| VaultError::DecryptionError("Failed to create key".to_string()))?;
+
+    let nonce_sequence = CounterNonceSequence::new(nonce_prefix);
+    let mut opening_key = OpeningKey::new(unbound_key, nonce_sequence);
+
+    // Decrypt the stream in chunks
+    let mut index: u64 = 0;
+    let mut saw_sentinel = false;
+    loop {
+        // Read chunk size
+        let chunk_size = match input.read_u32().await {
+            Ok(size) => size,
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e.into()),
+        };
+
+        let is_sentinel = version >= 3 && chunk_size == SENTINEL_LEN_MARKER;
+        let aad = Aad::from(if version >= 3 {
+            chunk_aad(&header_bytes, index, is_sentinel)
+        } else {
+            Vec::new()
+        });
+
+        // Read encrypted chunk
+        let mut encrypted_chunk = vec![0u8; if is_sentinel { 16 } else { chunk_size as usize }];
+        input.read_exact(&mut encrypted_chunk).await?;
+
+        // Decrypt chunk
+        let decrypted = opening_key
+            .open_in_place(aad, &mut encrypted_chunk)
+            .map_err(|_| VaultError::AuthenticationFailed)?;
+
+        if is_sentinel {
+            saw_sentinel = true;
+            break;
+        }
+
+        index += 1;
+
+        // Write decrypted data
+        output.write_all(decrypted).await?;
+    }
+
+    if version >= 3 && !saw_sentinel {
+        return Err(VaultError::InvalidFormat(
+            "Missing end-of-file sentinel (file may be truncated)".to_string(),
+        )
+        .into());
+    }
+
+    output.flush().await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+    use tokio::io::AsyncWriteExt;
+
+    async fn full_decrypt_file(
+        input: &mut DynAsyncRead,
+        output: &mut DynAsyncWrite,
+        password: &Zeroizing<String>,
+    ) -> Result<()> {
+        // version + mode + algorithm: mirrors what `CryptoEngine::decrypt_file`
+        // strips off before dispatching to this module.
+        let mut header = [0u8; 3];
+        input.read_exact(&mut header).await?;
+        decrypt_file(input, output, password, header[0]).await
+    }
+
+    #[tokio::test]
+    async fn test_encrypt_decrypt_round_trip() {
+        let password = Zeroizing::new("test_password".to_string());
+
+        // Create temp input file
+        let input_file = NamedTempFile::new().unwrap();
+        let input_path = input_file.path();
+
+        let test_data = b"Hello, World! This is a test with AES-256-GCM.";
+        let mut file = tokio::fs::File::create(input_path).await.unwrap();
+        file.write_all(test_data).await.unwrap();
+        file.flush().await.unwrap();
+        drop(file);
+
+        // Create temp output files
+        let encrypted_file = NamedTempFile::new().unwrap();
+        let encrypted_path = encrypted_file.path();
+
+        let decrypted_file = NamedTempFile::new().unwrap();
+        let decrypted_path = decrypted_file.path();
+
+        // Encrypt
+        let mut reader = tokio::fs::File::open(input_path).await.unwrap();
+        let mut writer = tokio::fs::File::create(encrypted_path).await.unwrap();
+        encrypt_file(&mut reader, &mut writer, &password, 1)
+            .await
+            .unwrap();
+
+        // Decrypt
+        let mut reader = tokio::fs::File::open(encrypted_path).await.unwrap();
+        let mut writer = tokio::fs::File::create(decrypted_path).await.unwrap();
+        full_decrypt_file(&mut reader, &mut writer, &password)
+            .await
+            .unwrap();
+
+        // Verify
+        let decrypted_data = tokio::fs::read(decrypted_path).await.unwrap();
+        assert_eq!(test_data, &decrypted_data[..]);
+    }
+
+    #[tokio::test]
+    async fn test_truncated_file_is_rejected() {
+        let password = Zeroizing::new("test_password".to_string());
+
+        let input_file = NamedTempFile::new().unwrap();
+        let input_path = input_file.path();
+        tokio::fs::write(input_path, b"some data to protect")
+            .await
+            .unwrap();
+
+        let encrypted_file = NamedTempFile::new().unwrap();
+        let encrypted_path = encrypted_file.path();
+
+        let mut reader = tokio::fs::File::open(input_path).await.unwrap();
+        let mut writer = tokio::fs::File::create(encrypted_path).await.unwrap();
+        encrypt_file(&mut reader, &mut writer, &password, 1)
+            .await
+            .unwrap();
+
+        // Drop the trailing sentinel chunk to simulate truncation
+        let mut bytes = tokio::fs::read(encrypted_path).await.unwrap();
+        let truncated_len = bytes.len() - 20;
+        bytes.truncate(truncated_len);
+        tokio::fs::write(encrypted_path, &bytes).await.unwrap();
+
+        let decrypted_file = NamedTempFile::new().unwrap();
+        let mut reader = tokio::fs::File::open(encrypted_path).await.unwrap();
+        let mut writer = tokio::fs::File::create(decrypted_file.path())
+            .await
+            .unwrap();
+        let result = full_decrypt_file(&mut reader, &mut writer, &password).await;
+
+        assert!(result.is_err());
+    }
+}
@@ -1,16 +1,22 @@
 use anyhow::Result;
+use chacha20poly1305::aead::generic_array::GenericArray;
+use chacha20poly1305::aead::{Aead, Payload};
+use chacha20poly1305::{KeyInit, XChaCha20Poly1305, XNonce};
 use rand::RngCore;
 use ring::aead::{Aad, BoundKey, Nonce, NonceSequence, OpeningKey, SealingKey, UnboundKey, CHACHA20_POLY1305};
 use ring::error::Unspecified;
-use std::path::Path;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use zeroize::Zeroizing;
 
-use super::{kdf, CHUNK_SIZE, SALT_SIZE, VERSION};
-use crate::crypto::Algorithm;
+use super::kdf;
+use super::{
+    chunk_aad, DynAsyncRead, DynAsyncWrite, CHUNK_SIZE, SALT_SIZE, SENTINEL_LEN_MARKER, VERSION,
+};
+use crate::crypto::kdf::KdfParams;
+use crate::crypto::{Algorithm, Mode};
 use crate::error::VaultError;
 
-/// Custom nonce sequence for ChaCha20
+/// Custom nonce sequence for the legacy (non-extended) ChaCha20-Poly1305 path
 struct CounterNonceSequence {
     counter: u64,
 }
@@ -30,133 +36,260 @@ impl NonceSequence for CounterNonceSequence {
     }
 }
 
-/// Encrypt a file using ChaCha20-Poly1305
+/// Build the 24-byte XChaCha20 nonce for a given chunk: the random per-file
+/// prefix followed by the chunk counter.
+fn chunk_nonce(prefix: &[u8; 16], counter: u64) -> XNonce {
+    let mut bytes = [0u8; 24];
+    bytes[..16].copy_from_slice(prefix);
+    bytes[16..].copy_from_slice(&counter.to_le_bytes());
+    *GenericArray::from_slice(&bytes)
+}
+
+/// Encrypt a stream using XChaCha20-Poly1305
 pub async fn encrypt_file(
-    input: &Path,
-    output: &Path,
+    input: &mut DynAsyncRead,
+    output: &mut DynAsyncWrite,
     password: &Zeroizing<String>,
     iterations: u32,
 ) -> Result<()> {
     // Generate random salt
     let mut salt = vec![0u8; SALT_SIZE];
     rand::thread_rng().fill_bytes(&mut salt);
-    
+
     // Derive key from password
-    let key = kdf::derive_key(password, &salt, iterations)?;
-    
-    // Generate random nonce (ChaCha20 uses 12 bytes with ring)
-    let mut nonce = [0u8; 12];
-    rand::thread_rng().fill_bytes(&mut nonce);
-    
-    // Open input file
-    let mut input_file = tokio::fs::File::open(input).await?;
-    
-    // Create output file
-    let mut output_file = tokio::fs::File::create(output).await?;
-    
-    // Write header: version (1 byte) + algorithm (1 byte) + salt + nonce
-    output_file.write_u8(VERSION).await?;
-    output_file.write_u8(Algorithm::ChaCha20Poly1305.to_byte()).await?;
-    output_file.write_all(&salt).await?;
-    output_file.write_all(&nonce).await?;
-    
-    // Create sealing key
-    let unbound_key = UnboundKey::new(&CHACHA20_POLY1305, &key)
-        .map_err(|_| VaultError::EncryptionError("Failed to create key".to_string()))?;
-    
-    let nonce_sequence = CounterNonceSequence::new();
-    let mut sealing_key = SealingKey::new(unbound_key, nonce_sequence);
-    
-    // Encrypt file in chunks
+    let params = KdfParams::new(iterations);
+    let key = kdf::derive_key(password, &salt, &params)?;
+
+    // Generate random 16-byte nonce prefix; each chunk's 24-byte nonce is
+    // `prefix || chunk_counter`
+    let mut nonce_prefix = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut nonce_prefix);
+
+    // Write header: version (1 byte) + mode (1 byte) + algorithm (1 byte) + salt + KDF params + nonce prefix
+    let mut header_bytes = Vec::with_capacity(3 + salt.len() + kdf::PARAMS_SIZE + nonce_prefix.len());
+    header_bytes.push(VERSION);
+    header_bytes.push(Mode::Password.to_byte());
+    header_bytes.push(Algorithm::XChaCha20Poly1305.to_byte());
+    header_bytes.extend_from_slice(&salt);
+    header_bytes.extend_from_slice(&params.to_bytes());
+    header_bytes.extend_from_slice(&nonce_prefix);
+    output.write_all(&header_bytes).await?;
+
+    let cipher = XChaCha20Poly1305::new(GenericArray::from_slice(&key));
+
+    // Encrypt the stream in chunks, binding the header and chunk index into
+    // each chunk's AAD
     let mut buffer = vec![0u8; CHUNK_SIZE];
-    
+    let mut index: u64 = 0;
+
     loop {
-        let bytes_read = input_file.read(&mut buffer).await?;
+        let bytes_read = input.read(&mut buffer).await?;
         if bytes_read == 0 {
             break;
         }
-        
-        let mut in_out = buffer[..bytes_read].to_vec();
-        
-        sealing_key
-            .seal_in_place_append_tag(Aad::empty(), &mut in_out)
+
+        let nonce = chunk_nonce(&nonce_prefix, index);
+        let aad = chunk_aad(&header_bytes, index, false);
+        index += 1;
+
+        let ciphertext = cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: &buffer[..bytes_read],
+                    aad: &aad,
+                },
+            )
             .map_err(|_| VaultError::EncryptionError("Encryption failed".to_string()))?;
-        
+
         // Write chunk size (4 bytes) + encrypted chunk
-        output_file.write_u32(in_out.len() as u32).await?;
-        output_file.write_all(&in_out).await?;
+        output.write_u32(ciphertext.len() as u32).await?;
+        output.write_all(&ciphertext).await?;
     }
-    
-    output_file.flush().await?;
-    
+
+    // Append the truncation sentinel: a zero-length chunk authenticated under
+    // the true count of data chunks that preceded it (so it can't be spliced
+    // to an earlier point in the stream), flagged on disk with a `u32::MAX`
+    // length marker so it can't be confused with a real (and necessarily
+    // shorter) chunk.
+    let sentinel_nonce = chunk_nonce(&nonce_prefix, index);
+    let sentinel_aad = chunk_aad(&header_bytes, index, true);
+    let sentinel = cipher
+        .encrypt(
+            &sentinel_nonce,
+            Payload {
+                msg: &[],
+                aad: &sentinel_aad,
+            },
+        )
+        .map_err(|_| VaultError::EncryptionError("Encryption failed".to_string()))?;
+    output.write_u32(SENTINEL_LEN_MARKER).await?;
+    output.write_all(&sentinel).await?;
+
+    output.flush().await?;
+
     Ok(())
 }
 
-/// Decrypt a file using ChaCha20-Poly1305
+/// Decrypt a stream using XChaCha20-Poly1305. `version` is the file format
+/// version already read (and validated) by the caller from the first header
+/// byte.
 pub async fn decrypt_file(
-    input: &Path,
-    output: &Path,
+    input: &mut DynAsyncRead,
+    output: &mut DynAsyncWrite,
     password: &Zeroizing<String>,
+    version: u8,
 ) -> Result<()> {
-    // Open input file
-    let mut input_file = tokio::fs::File::open(input).await?;
-    
-    // Read header
-    let version = input_file.read_u8().await?;
-    if version != VERSION {
-        return Err(VaultError::UnsupportedVersion(version).into());
+    // Read salt
+    let mut salt = vec![0u8; SALT_SIZE];
+    input.read_exact(&mut salt).await?;
+
+    // Version 2+ stores the exact KDF params used at encryption time
+    let mut params_bytes = [0u8; kdf::PARAMS_SIZE];
+    input.read_exact(&mut params_bytes).await?;
+    let params = KdfParams::from_bytes(&params_bytes)?;
+
+    // Read nonce prefix
+    let mut nonce_prefix = [0u8; 16];
+    input.read_exact(&mut nonce_prefix).await?;
+
+    // Recompute the exact header bytes so version 3+ chunks can be
+    // re-authenticated against the same AAD used at encryption time. Version
+    // 4+ headers carry a mode byte that versions 1-3 never wrote.
+    let mut header_bytes = Vec::with_capacity(3 + salt.len() + kdf::PARAMS_SIZE + nonce_prefix.len());
+    header_bytes.push(version);
+    if version >= 4 {
+        header_bytes.push(Mode::Password.to_byte());
     }
-    
-    let algorithm_byte = input_file.read_u8().await?;
-    if algorithm_byte != Algorithm::ChaCha20Poly1305.to_byte() {
-        return Err(VaultError::InvalidFormat("Wrong algorithm".to_string()).into());
+    header_bytes.push(Algorithm::XChaCha20Poly1305.to_byte());
+    header_bytes.extend_from_slice(&salt);
+    header_bytes.extend_from_slice(&params.to_bytes());
+    header_bytes.extend_from_slice(&nonce_prefix);
+
+    // Derive key from password
+    let key = kdf::derive_key(password, &salt, &params)?;
+    let cipher = XChaCha20Poly1305::new(GenericArray::from_slice(&key));
+
+    // Decrypt the stream in chunks
+    let mut index: u64 = 0;
+    let mut saw_sentinel = false;
+    loop {
+        // Read chunk size
+        let chunk_size = match input.read_u32().await {
+            Ok(size) => size,
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e.into()),
+        };
+
+        let is_sentinel = version >= 3 && chunk_size == SENTINEL_LEN_MARKER;
+        let nonce = chunk_nonce(&nonce_prefix, index);
+        let aad = if version >= 3 {
+            chunk_aad(&header_bytes, index, is_sentinel)
+        } else {
+            Vec::new()
+        };
+
+        // Read encrypted chunk
+        let read_len = if is_sentinel { 16 } else { chunk_size as usize };
+        let mut encrypted_chunk = vec![0u8; read_len];
+        input.read_exact(&mut encrypted_chunk).await?;
+
+        // Decrypt chunk
+        let decrypted = cipher
+            .decrypt(
+                &nonce,
+                Payload {
+                    msg: &encrypted_chunk,
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| VaultError::AuthenticationFailed)?;
+
+        if is_sentinel {
+            saw_sentinel = true;
+            break;
+        }
+
+        index += 1;
+
+        // Write decrypted data
+        output.write_all(&decrypted).await?;
     }
-    
+
+    if version >= 3 && !saw_sentinel {
+        return Err(VaultError::InvalidFormat(
+            "Missing end-of-file sentinel (file may be truncated)".to_string(),
+        )
+        .into());
+    }
+
+    output.flush().await?;
+
+    Ok(())
+}
+
+/// Decrypt a stream written by the legacy (pre-XChaCha20) ChaCha20-Poly1305
+/// path, identified by algorithm byte 2. New encryptions never produce this
+/// format; this exists solely so old files stay decryptable. `version` is
+/// the file format version already read (and validated) by the caller.
+pub async fn decrypt_file_legacy(
+    input: &mut DynAsyncRead,
+    output: &mut DynAsyncWrite,
+    password: &Zeroizing<String>,
+    version: u8,
+) -> Result<()> {
     // Read salt
     let mut salt = vec![0u8; SALT_SIZE];
-    input_file.read_exact(&mut salt).await?;
-    
+    input.read_exact(&mut salt).await?;
+
+    // Version 2+ stores the exact KDF params used at encryption time; version 1
+    // files hardcoded them, so fall back to those defaults.
+    let params = if version >= 2 {
+        let mut params_bytes = [0u8; kdf::PARAMS_SIZE];
+        input.read_exact(&mut params_bytes).await?;
+        KdfParams::from_bytes(&params_bytes)?
+    } else {
+        KdfParams::legacy_default()
+    };
+
     // Read nonce
     let mut nonce = [0u8; 12];
-    input_file.read_exact(&mut nonce).await?;
-    
+    input.read_exact(&mut nonce).await?;
+
     // Derive key from password
-    let key = kdf::derive_key(password, &salt, 3)?; // Use default iterations for decryption
-    
+    let key = kdf::derive_key(password, &salt, &params)?;
+
     // Create opening key
     let unbound_key = UnboundKey::new(&CHACHA20_POLY1305, &key)
         .map_err(|_| VaultError::DecryptionError("Failed to create key".to_string()))?;
-    
+
     let nonce_sequence = CounterNonceSequence::new();
     let mut opening_key = OpeningKey::new(unbound_key, nonce_sequence);
-    
-    // Create output file
-    let mut output_file = tokio::fs::File::create(output).await?;
-    
-    // Decrypt file in chunks
+
+    // Decrypt the stream in chunks
     loop {
         // Read chunk size
-        let chunk_size = match input_file.read_u32().await {
+        let chunk_size = match input.read_u32().await {
             Ok(size) => size as usize,
             Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
             Err(e) => return Err(e.into()),
         };
-        
+
         // Read encrypted chunk
         let mut encrypted_chunk = vec![0u8; chunk_size];
-        input_file.read_exact(&mut encrypted_chunk).await?;
-        
+        input.read_exact(&mut encrypted_chunk).await?;
+
         // Decrypt chunk
         let decrypted = opening_key
             .open_in_place(Aad::empty(), &mut encrypted_chunk)
             .map_err(|_| VaultError::AuthenticationFailed)?;
-        
+
         // Write decrypted data
-        output_file.write_all(decrypted).await?;
+        output.write_all(decrypted).await?;
     }
-    
-    output_file.flush().await?;
-    
+
+    output.flush().await?;
+
     Ok(())
 }
 
@@ -165,40 +298,101 @@ mod tests {
     use super::*;
     use tempfile::NamedTempFile;
     use tokio::io::AsyncWriteExt;
-    
+
+    async fn full_decrypt_file(
+        input: &mut DynAsyncRead,
+        output: &mut DynAsyncWrite,
+        password: &Zeroizing<String>,
+    ) -> Result<()> {
+        // version + mode + algorithm: mirrors what `CryptoEngine::decrypt_file`
+        // strips off before dispatching to this module.
+        let mut header = [0u8; 3];
+        input.read_exact(&mut header).await?;
+        decrypt_file(input, output, password, header[0]).await
+    }
+
     #[tokio::test]
     async fn test_encrypt_decrypt_round_trip() {
         let password = Zeroizing::new("test_password".to_string());
-        
+
         // Create temp input file
         let input_file = NamedTempFile::new().unwrap();
         let input_path = input_file.path();
-        
-        let test_data = b"Hello, World! This is a test with ChaCha20.";
+
+        let test_data = b"Hello, World! This is a test with XChaCha20.";
         let mut file = tokio::fs::File::create(input_path).await.unwrap();
         file.write_all(test_data).await.unwrap();
         file.flush().await.unwrap();
         drop(file);
-        
+
         // Create temp output files
         let encrypted_file = NamedTempFile::new().unwrap();
         let encrypted_path = encrypted_file.path();
-        
+
         let decrypted_file = NamedTempFile::new().unwrap();
         let decrypted_path = decrypted_file.path();
-        
+
         // Encrypt
-        encrypt_file(input_path, encrypted_path, &password, 1)
+        let mut reader = tokio::fs::File::open(input_path).await.unwrap();
+        let mut writer = tokio::fs::File::create(encrypted_path).await.unwrap();
+        encrypt_file(&mut reader, &mut writer, &password, 1)
             .await
             .unwrap();
-        
+
         // Decrypt
-        decrypt_file(encrypted_path, decrypted_path, &password)
+        let mut reader = tokio::fs::File::open(encrypted_path).await.unwrap();
+        let mut writer = tokio::fs::File::create(decrypted_path).await.unwrap();
+        full_decrypt_file(&mut reader, &mut writer, &password)
             .await
             .unwrap();
-        
+
         // Verify
         let decrypted_data = tokio::fs::read(decrypted_path).await.unwrap();
         assert_eq!(test_data, &decrypted_data[..]);
     }
+
+    #[test]
+    fn test_chunk_nonce_varies_by_counter() {
+        let prefix = [7u8; 16];
+        let nonce0 = chunk_nonce(&prefix, 0);
+        let nonce1 = chunk_nonce(&prefix, 1);
+
+        assert_ne!(nonce0, nonce1);
+        assert_eq!(&nonce0[..16], &prefix[..]);
+    }
+
+    #[tokio::test]
+    async fn test_truncated_file_is_rejected() {
+        let password = Zeroizing::new("test_password".to_string());
+
+        let input_file = NamedTempFile::new().unwrap();
+        let input_path = input_file.path();
+        tokio::fs::write(input_path, b"some data to protect")
+            .await
+            .unwrap();
+
+        let encrypted_file = NamedTempFile::new().unwrap();
+        let encrypted_path = encrypted_file.path();
+
+        let mut reader = tokio::fs::File::open(input_path).await.unwrap();
+        let mut writer = tokio::fs::File::create(encrypted_path).await.unwrap();
+        encrypt_file(&mut reader, &mut writer, &password, 1)
+            .await
+            .unwrap();
+
+        // Drop the trailing sentinel chunk to simulate truncation
+        let mut bytes = tokio::fs::read(encrypted_path).await.unwrap();
+        let truncated_len = bytes.len() - 20;
+        bytes.truncate(truncated_len);
+        tokio::fs::write(encrypted_path, &bytes).await.unwrap();
+
+        let decrypted_file = NamedTempFile::new().unwrap();
+        let mut reader = tokio::fs::File::open(encrypted_path).await.unwrap();
+        let mut writer = tokio::fs::File::create(decrypted_file.path())
+            .await
+            .unwrap();
+        let result = full_decrypt_file(&mut reader, &mut writer, &password).await;
+
+        assert!(result.is_err());
+    }
 }
@@ -7,25 +7,103 @@ use zeroize::Zeroizing;
 
 use crate::error::VaultError;
 
+/// Size in bytes of the serialized `KdfParams` block stored in the file header.
+pub const PARAMS_SIZE: usize = 12;
+
+/// Upper bounds on Argon2id parameters read back from an untrusted file
+/// header. Without these, a corrupted or malicious file could set
+/// `t_cost`/`m_cost`/`p_cost` to huge values and force `derive_key` to spend
+/// ages iterating, allocate gigabytes of memory, or spin up an absurd number
+/// of lanes — a resource-exhaustion DoS on anyone who opens the file.
+const MAX_T_COST: u32 = 100;
+const MAX_M_COST: u32 = 1024 * 1024; // 1 GiB, in KiB
+const MAX_P_COST: u32 = 16;
+
+/// Argon2id parameters, persisted in the file header so a file is always
+/// decrypted with the exact parameters it was encrypted with.
+#[derive(Debug, Clone, Copy)]
+pub struct KdfParams {
+    pub t_cost: u32,
+    pub m_cost: u32,
+    pub p_cost: u32,
+    pub output_len: u32,
+}
+
+impl KdfParams {
+    /// Parameters for a fresh encryption: the repo's fixed memory/parallelism
+    /// cost, with the caller only choosing the iteration count.
+    pub fn new(iterations: u32) -> Self {
+        Self {
+            t_cost: iterations,
+            m_cost: 65536,
+            p_cost: 4,
+            output_len: 32,
+        }
+    }
+
+    /// Parameters assumed for version 1 files, which hardcoded these values.
+    pub fn legacy_default() -> Self {
+        Self::new(3)
+    }
+
+    /// Serialize `t_cost`/`m_cost`/`p_cost` as little-endian `u32`s.
+    /// `output_len` isn't stored: every version of this format derives a
+    /// fixed 32-byte key.
+    pub fn to_bytes(&self) -> [u8; PARAMS_SIZE] {
+        let mut bytes = [0u8; PARAMS_SIZE];
+        bytes[0..4].copy_from_slice(&self.t_cost.to_le_bytes());
+        bytes[4..8].copy_from_slice(&self.m_cost.to_le_bytes());
+        bytes[8..12].copy_from_slice(&self.p_cost.to_le_bytes());
+        bytes
+    }
+
+    /// Parse a `KdfParams` block read from an untrusted file header, rejecting
+    /// out-of-range values instead of handing them to Argon2 (see
+    /// `MAX_T_COST`/`MAX_M_COST`/`MAX_P_COST`).
+    pub fn from_bytes(bytes: &[u8; PARAMS_SIZE]) -> Result<Self> {
+        let params = Self {
+            t_cost: u32::from_le_bytes(bytes[0..4].try_into().unwrap()),
+            m_cost: u32::from_le_bytes(bytes[4..8].try_into().unwrap()),
+            p_cost: u32::from_le_bytes(bytes[8..12].try_into().unwrap()),
+            output_len: 32,
+        };
+
+        if params.t_cost == 0
+            || params.t_cost > MAX_T_COST
+            || params.m_cost > MAX_M_COST
+            || params.p_cost == 0
+            || params.p_cost > MAX_P_COST
+        {
+            return Err(VaultError::InvalidFormat(format!(
+                "KDF parameters out of range (t_cost={}, m_cost={}, p_cost={})",
+                params.t_cost, params.m_cost, params.p_cost
+            ))
+            .into());
+        }
+
+        Ok(params)
+    }
+}
+
 /// Derive encryption key from password using Argon2id
 pub fn derive_key(
     password: &Zeroizing<String>,
     salt: &[u8],
-    iterations: u32,
+    params: &KdfParams,
 ) -> Result<Zeroizing<Vec<u8>>> {
     // Configure Argon2id parameters
-    let params = ParamsBuilder::new()
-        .m_cost(65536) // 64 MB memory
-        .t_cost(iterations) // iterations
-        .p_cost(4) // 4 parallel threads
-        .output_len(32) // 32 bytes = 256 bits
+    let argon2_params = ParamsBuilder::new()
+        .m_cost(params.m_cost) // memory cost (KiB)
+        .t_cost(params.t_cost) // iterations
+        .p_cost(params.p_cost) // parallel threads
+        .output_len(params.output_len as usize)
         .build()
         .map_err(|e| VaultError::KdfError(e.to_string()))?;
-    
+
     let argon2 = Argon2::new(
         argon2::Algorithm::Argon2id,
         Version::V0x13,
-        params,
+        argon2_params,
     );
     
     // Create salt string from bytes
@@ -54,31 +132,61 @@ mod tests {
         let password = Zeroizing::new("test_password".to_string());
         let salt = b"test_salt_32_bytes_long_exactly!";
         
-        let key = derive_key(&password, salt, 1).unwrap();
-        
+        let params = KdfParams::new(1);
+        let key = derive_key(&password, salt, &params).unwrap();
+
         assert_eq!(key.len(), 32);
     }
-    
+
     #[test]
     fn test_derive_key_deterministic() {
         let password = Zeroizing::new("test_password".to_string());
         let salt = b"test_salt_32_bytes_long_exactly!";
-        
-        let key1 = derive_key(&password, salt, 1).unwrap();
-        let key2 = derive_key(&password, salt, 1).unwrap();
-        
+        let params = KdfParams::new(1);
+
+        let key1 = derive_key(&password, salt, &params).unwrap();
+        let key2 = derive_key(&password, salt, &params).unwrap();
+
         assert_eq!(*key1, *key2);
     }
-    
+
     #[test]
     fn test_derive_key_different_passwords() {
         let password1 = Zeroizing::new("password1".to_string());
         let password2 = Zeroizing::new("password2".to_string());
         let salt = b"test_salt_32_bytes_long_exactly!";
-        
-        let key1 = derive_key(&password1, salt, 1).unwrap();
-        let key2 = derive_key(&password2, salt, 1).unwrap();
-        
+        let params = KdfParams::new(1);
+
+        let key1 = derive_key(&password1, salt, &params).unwrap();
+        let key2 = derive_key(&password2, salt, &params).unwrap();
+
         assert_ne!(*key1, *key2);
     }
+
+    #[test]
+    fn test_kdf_params_round_trip() {
+        let params = KdfParams::new(5);
+        let bytes = params.to_bytes();
+        let decoded = KdfParams::from_bytes(&bytes).unwrap();
+
+        assert_eq!(decoded.t_cost, 5);
+        assert_eq!(decoded.m_cost, params.m_cost);
+        assert_eq!(decoded.p_cost, params.p_cost);
+    }
+
+    #[test]
+    fn test_kdf_params_rejects_oversized_m_cost() {
+        let mut params = KdfParams::new(5);
+        params.m_cost = MAX_M_COST + 1;
+
+        assert!(KdfParams::from_bytes(&params.to_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_kdf_params_rejects_oversized_p_cost() {
+        let mut params = KdfParams::new(5);
+        params.p_cost = MAX_P_COST + 1;
+
+        assert!(KdfParams::from_bytes(&params.to_bytes()).is_err());
+    }
 }
@@ -0,0 +1,362 @@
+use anyhow::Result;
+use chacha20poly1305::aead::generic_array::GenericArray;
+use chacha20poly1305::aead::{Aead, Payload};
+use chacha20poly1305::{KeyInit, XChaCha20Poly1305, XNonce};
+use hkdf::Hkdf;
+use rand::RngCore;
+use sha2::Sha256;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use x25519_dalek::{EphemeralSecret, PublicKey, StaticSecret};
+
+use super::{chunk_aad, DynAsyncRead, DynAsyncWrite, Mode, CHUNK_SIZE, SENTINEL_LEN_MARKER, VERSION};
+use crate::crypto::Algorithm;
+use crate::error::VaultError;
+
+/// Size in bytes of an X25519 public key.
+pub const PUBLIC_KEY_SIZE: usize = 32;
+
+/// Size in bytes of an X25519 secret key.
+pub const SECRET_KEY_SIZE: usize = 32;
+
+/// HKDF info string binding the derived content key to this scheme, so the
+/// raw ECDH shared secret is never used as a key directly.
+const HKDF_INFO: &[u8] = b"secure-vault recipient content key v1";
+
+/// Generate a new X25519 keypair for recipient-mode encryption. Returns
+/// `(public_key, secret_key)`.
+pub fn generate_keypair() -> ([u8; PUBLIC_KEY_SIZE], [u8; SECRET_KEY_SIZE]) {
+    let secret = StaticSecret::random_from_rng(rand::thread_rng());
+    let public = PublicKey::from(&secret);
+    (public.to_bytes(), secret.to_bytes())
+}
+
+/// Derive the 256-bit content key for a recipient-mode file from the X25519
+/// shared secret, binding in both public keys via the HKDF salt so the
+/// derived key is unique to this ephemeral/recipient pairing.
+fn derive_content_key(
+    shared_secret: &[u8; 32],
+    ephemeral_public: &[u8; PUBLIC_KEY_SIZE],
+    recipient_public: &[u8; PUBLIC_KEY_SIZE],
+) -> [u8; 32] {
+    let mut salt = Vec::with_capacity(PUBLIC_KEY_SIZE * 2);
+    salt.extend_from_slice(ephemeral_public);
+    salt.extend_from_slice(recipient_public);
+
+    let hk = Hkdf::<Sha256>::new(Some(&salt), shared_secret);
+    let mut key = [0u8; 32];
+    hk.expand(HKDF_INFO, &mut key)
+        .expect("32 is a valid HKDF-SHA256 output length");
+    key
+}
+
+/// Build the 24-byte XChaCha20 nonce for a given chunk: the random per-file
+/// prefix followed by the chunk counter (mirrors `chacha::chunk_nonce`).
+fn chunk_nonce(prefix: &[u8; 16], counter: u64) -> XNonce {
+    let mut bytes = [0u8; 24];
+    bytes[..16].copy_from_slice(prefix);
+    bytes[16..].copy_from_slice(&counter.to_le_bytes());
+    *GenericArray::from_slice(&bytes)
+}
+
+/// Encrypt a stream to `recipient_public` using ephemeral X25519 ECDH,
+/// HKDF-SHA256, and XChaCha20-Poly1305 — no password involved. The ephemeral
+/// public key is stored in the header so the recipient can recompute the
+/// same shared secret from their own secret key.
+pub async fn encrypt_file(
+    input: &mut DynAsyncRead,
+    output: &mut DynAsyncWrite,
+    recipient_public: &[u8; PUBLIC_KEY_SIZE],
+) -> Result<()> {
+    let ephemeral_secret = EphemeralSecret::random_from_rng(rand::thread_rng());
+    let ephemeral_public = PublicKey::from(&ephemeral_secret);
+    let shared_secret = ephemeral_secret.diffie_hellman(&PublicKey::from(*recipient_public));
+
+    let key = derive_content_key(
+        shared_secret.as_bytes(),
+        ephemeral_public.as_bytes(),
+        recipient_public,
+    );
+
+    // Generate random 16-byte nonce prefix; each chunk's 24-byte nonce is
+    // `prefix || chunk_counter`
+    let mut nonce_prefix = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut nonce_prefix);
+
+    // Write header: version + mode + algorithm + ephemeral public key + nonce prefix
+    let mut header_bytes =
+        Vec::with_capacity(3 + PUBLIC_KEY_SIZE + nonce_prefix.len());
+    header_bytes.push(VERSION);
+    header_bytes.push(Mode::Recipient.to_byte());
+    header_bytes.push(Algorithm::XChaCha20Poly1305.to_byte());
+    header_bytes.extend_from_slice(ephemeral_public.as_bytes());
+    header_bytes.extend_from_slice(&nonce_prefix);
+    output.write_all(&header_bytes).await?;
+
+    let cipher = XChaCha20Poly1305::new(GenericArray::from_slice(&key));
+
+    // Encrypt the stream in chunks, binding the header and chunk index into
+    // each chunk's AAD
+    let mut buffer = vec![0u8; CHUNK_SIZE];
+    let mut index: u64 = 0;
+
+    loop {
+        let bytes_read = input.read(&mut buffer).await?;
+        if bytes_read == 0 {
+            break;
+        }
+
+        let nonce = chunk_nonce(&nonce_prefix, index);
+        let aad = chunk_aad(&header_bytes, index, false);
+        index += 1;
+
+        let ciphertext = cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: &buffer[..bytes_read],
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| VaultError::EncryptionError("Encryption failed".to_string()))?;
+
+        output.write_u32(ciphertext.len() as u32).await?;
+        output.write_all(&ciphertext).await?;
+    }
+
+    // Append the truncation sentinel, matching the scheme used by the
+    // password-mode algorithms (see `chacha::encrypt_file`): authenticated
+    // under the true count of data chunks that preceded it, so it can't be
+    // spliced to an earlier point in the stream.
+    let sentinel_nonce = chunk_nonce(&nonce_prefix, index);
+    let sentinel_aad = chunk_aad(&header_bytes, index, true);
+    let sentinel = cipher
+        .encrypt(
+            &sentinel_nonce,
+            Payload {
+                msg: &[],
+                aad: &sentinel_aad,
+            },
+        )
+        .map_err(|_| VaultError::EncryptionError("Encryption failed".to_string()))?;
+    output.write_u32(SENTINEL_LEN_MARKER).await?;
+    output.write_all(&sentinel).await?;
+
+    output.flush().await?;
+
+    Ok(())
+}
+
+/// Decrypt a stream encrypted to `identity_secret` by [`encrypt_file`].
+/// `version` is the file format version already read (and validated) by the
+/// caller from the first header byte.
+pub async fn decrypt_file(
+    input: &mut DynAsyncRead,
+    output: &mut DynAsyncWrite,
+    identity_secret: &[u8; SECRET_KEY_SIZE],
+    version: u8,
+) -> Result<()> {
+    // Recipient mode only ever uses XChaCha20-Poly1305
+    let mut algorithm_byte = [0u8; 1];
+    input.read_exact(&mut algorithm_byte).await?;
+    if algorithm_byte[0] != Algorithm::XChaCha20Poly1305.to_byte() {
+        return Err(VaultError::UnsupportedAlgorithm(algorithm_byte[0]).into());
+    }
+
+    let mut ephemeral_public_bytes = [0u8; PUBLIC_KEY_SIZE];
+    input.read_exact(&mut ephemeral_public_bytes).await?;
+
+    let mut nonce_prefix = [0u8; 16];
+    input.read_exact(&mut nonce_prefix).await?;
+
+    // Recompute the exact header bytes so chunks can be re-authenticated
+    // against the same AAD used at encryption time.
+    let mut header_bytes =
+        Vec::with_capacity(3 + PUBLIC_KEY_SIZE + nonce_prefix.len());
+    header_bytes.push(version);
+    header_bytes.push(Mode::Recipient.to_byte());
+    header_bytes.push(algorithm_byte[0]);
+    header_bytes.extend_from_slice(&ephemeral_public_bytes);
+    header_bytes.extend_from_slice(&nonce_prefix);
+
+    let secret = StaticSecret::from(*identity_secret);
+    let recipient_public = PublicKey::from(&secret);
+    let ephemeral_public = PublicKey::from(ephemeral_public_bytes);
+    let shared_secret = secret.diffie_hellman(&ephemeral_public);
+
+    let key = derive_content_key(
+        shared_secret.as_bytes(),
+        &ephemeral_public_bytes,
+        recipient_public.as_bytes(),
+    );
+    let cipher = XChaCha20Poly1305::new(GenericArray::from_slice(&key));
+
+    // Decrypt the stream in chunks
+    let mut index: u64 = 0;
+    let mut saw_sentinel = false;
+    loop {
+        let chunk_size = match input.read_u32().await {
+            Ok(size) => size,
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e.into()),
+        };
+
+        let is_sentinel = chunk_size == SENTINEL_LEN_MARKER;
+        let nonce = chunk_nonce(&nonce_prefix, index);
+        let aad = chunk_aad(&header_bytes, index, is_sentinel);
+
+        let read_len = if is_sentinel { 16 } else { chunk_size as usize };
+        let mut encrypted_chunk = vec![0u8; read_len];
+        input.read_exact(&mut encrypted_chunk).await?;
+
+        let decrypted = cipher
+            .decrypt(
+                &nonce,
+                Payload {
+                    msg: &encrypted_chunk,
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| VaultError::AuthenticationFailed)?;
+
+        if is_sentinel {
+            saw_sentinel = true;
+            break;
+        }
+
+        index += 1;
+        output.write_all(&decrypted).await?;
+    }
+
+    if !saw_sentinel {
+        return Err(VaultError::InvalidFormat(
+            "Missing end-of-file sentinel (file may be truncated)".to_string(),
+        )
+        .into());
+    }
+
+    output.flush().await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+    use tokio::io::AsyncWriteExt;
+
+    async fn full_decrypt_file(
+        input: &mut DynAsyncRead,
+        output: &mut DynAsyncWrite,
+        identity_secret: &[u8; SECRET_KEY_SIZE],
+    ) -> Result<()> {
+        // version + mode: mirrors what `CryptoEngine::decrypt_file_recipient`
+        // strips off before dispatching to this module.
+        let mut header = [0u8; 2];
+        input.read_exact(&mut header).await?;
+        decrypt_file(input, output, identity_secret, header[0]).await
+    }
+
+    #[tokio::test]
+    async fn test_encrypt_decrypt_round_trip() {
+        let (recipient_public, recipient_secret) = generate_keypair();
+
+        let input_file = NamedTempFile::new().unwrap();
+        let input_path = input_file.path();
+
+        let test_data = b"Hello, World! This is a test with recipient-mode encryption.";
+        let mut file = tokio::fs::File::create(input_path).await.unwrap();
+        file.write_all(test_data).await.unwrap();
+        file.flush().await.unwrap();
+        drop(file);
+
+        let encrypted_file = NamedTempFile::new().unwrap();
+        let encrypted_path = encrypted_file.path();
+
+        let decrypted_file = NamedTempFile::new().unwrap();
+        let decrypted_path = decrypted_file.path();
+
+        // Encrypt
+        let mut reader = tokio::fs::File::open(input_path).await.unwrap();
+        let mut writer = tokio::fs::File::create(encrypted_path).await.unwrap();
+        encrypt_file(&mut reader, &mut writer, &recipient_public)
+            .await
+            .unwrap();
+
+        // Decrypt
+        let mut reader = tokio::fs::File::open(encrypted_path).await.unwrap();
+        let mut writer = tokio::fs::File::create(decrypted_path).await.unwrap();
+        full_decrypt_file(&mut reader, &mut writer, &recipient_secret)
+            .await
+            .unwrap();
+
+        // Verify
+        let decrypted_data = tokio::fs::read(decrypted_path).await.unwrap();
+        assert_eq!(test_data, &decrypted_data[..]);
+    }
+
+    #[tokio::test]
+    async fn test_wrong_identity_key_fails() {
+        let (recipient_public, _recipient_secret) = generate_keypair();
+        let (_other_public, other_secret) = generate_keypair();
+
+        let input_file = NamedTempFile::new().unwrap();
+        let input_path = input_file.path();
+        tokio::fs::write(input_path, b"some data to protect")
+            .await
+            .unwrap();
+
+        let encrypted_file = NamedTempFile::new().unwrap();
+        let encrypted_path = encrypted_file.path();
+
+        let mut reader = tokio::fs::File::open(input_path).await.unwrap();
+        let mut writer = tokio::fs::File::create(encrypted_path).await.unwrap();
+        encrypt_file(&mut reader, &mut writer, &recipient_public)
+            .await
+            .unwrap();
+
+        let decrypted_file = NamedTempFile::new().unwrap();
+        let mut reader = tokio::fs::File::open(encrypted_path).await.unwrap();
+        let mut writer = tokio::fs::File::create(decrypted_file.path())
+            .await
+            .unwrap();
+        let result = full_decrypt_file(&mut reader, &mut writer, &other_secret).await;
+
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_truncated_file_is_rejected() {
+        let (recipient_public, recipient_secret) = generate_keypair();
+
+        let input_file = NamedTempFile::new().unwrap();
+        let input_path = input_file.path();
+        tokio::fs::write(input_path, b"some data to protect")
+            .await
+            .unwrap();
+
+        let encrypted_file = NamedTempFile::new().unwrap();
+        let encrypted_path = encrypted_file.path();
+
+        let mut reader = tokio::fs::File::open(input_path).await.unwrap();
+        let mut writer = tokio::fs::File::create(encrypted_path).await.unwrap();
+        encrypt_file(&mut reader, &mut writer, &recipient_public)
+            .await
+            .unwrap();
+
+        // Drop the trailing sentinel chunk to simulate truncation
+        let mut bytes = tokio::fs::read(encrypted_path).await.unwrap();
+        let truncated_len = bytes.len() - 20;
+        bytes.truncate(truncated_len);
+        tokio::fs::write(encrypted_path, &bytes).await.unwrap();
+
+        let decrypted_file = NamedTempFile::new().unwrap();
+        let mut reader = tokio::fs::File::open(encrypted_path).await.unwrap();
+        let mut writer = tokio::fs::File::create(decrypted_file.path())
+            .await
+            .unwrap();
+        let result = full_decrypt_file(&mut reader, &mut writer, &recipient_secret).await;
+
+        assert!(result.is_err());
+    }
+}
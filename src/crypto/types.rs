@@ -2,15 +2,23 @@ use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
 
 /// Supported encryption algorithms
-#[derive(Debug, Clone, Copy, ValueEnum, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
 pub enum Algorithm {
     /// AES-256-GCM (default)
     #[value(name = "aes256gcm")]
     Aes256Gcm,
-    
-    /// ChaCha20-Poly1305
-    #[value(name = "chacha20")]
+
+    /// ChaCha20-Poly1305 with a 12-byte nonce. Kept only so files written by
+    /// older versions of this tool remain decryptable; new encryptions use
+    /// `XChaCha20Poly1305` instead.
+    #[value(skip)]
     ChaCha20Poly1305,
+
+    /// XChaCha20-Poly1305: ChaCha20-Poly1305 with an extended 24-byte nonce,
+    /// wide enough to pick a random per-file prefix with no practical risk of
+    /// reuse across files encrypted under the same key.
+    #[value(name = "chacha20")]
+    XChaCha20Poly1305,
 }
 
 impl Algorithm {
@@ -19,29 +27,65 @@ impl Algorithm {
         match self {
             Algorithm::Aes256Gcm => 1,
             Algorithm::ChaCha20Poly1305 => 2,
+            Algorithm::XChaCha20Poly1305 => 3,
         }
     }
-    
+
     /// Convert byte to algorithm
     pub fn from_byte(byte: u8) -> Option<Self> {
         match byte {
             1 => Some(Algorithm::Aes256Gcm),
             2 => Some(Algorithm::ChaCha20Poly1305),
+            3 => Some(Algorithm::XChaCha20Poly1305),
             _ => None,
         }
     }
-    
-    /// Get nonce size for algorithm
-    pub fn nonce_size(&self) -> usize {
+
+    /// Size in bytes of the random nonce prefix stored in the header. The
+    /// per-chunk AEAD nonce is formed as `prefix || chunk_counter` (see the
+    /// `aes`/`chacha` modules), so this is smaller than the algorithm's full
+    /// nonce size. The legacy `ChaCha20Poly1305` format is the exception: its
+    /// header holds a full 12-byte nonce that its per-chunk nonces don't use.
+    pub fn nonce_prefix_size(&self) -> usize {
         match self {
-            Algorithm::Aes256Gcm => 12,
-            Algorithm::ChaCha20Poly1305 => 24,
+            Algorithm::Aes256Gcm => 4,
+            Algorithm::ChaCha20Poly1305 => 12,
+            Algorithm::XChaCha20Poly1305 => 16,
         }
     }
-    
+
     /// Get tag size for algorithm
     pub fn tag_size(&self) -> usize {
-        16 // Both algorithms use 16-byte tags
+        16 // All algorithms use 16-byte tags
+    }
+}
+
+/// Encryption mode: password-based (Argon2id-derived key) or recipient
+/// public-key based (X25519 ECDH + HKDF-derived key, see `crypto::recipient`).
+/// Only meaningful for version 4+ files; every earlier version is implicitly
+/// `Password`, since recipient mode didn't exist yet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Password,
+    Recipient,
+}
+
+impl Mode {
+    /// Convert mode to byte representation
+    pub fn to_byte(self) -> u8 {
+        match self {
+            Mode::Password => 1,
+            Mode::Recipient => 2,
+        }
+    }
+
+    /// Convert byte to mode
+    pub fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            1 => Some(Mode::Password),
+            2 => Some(Mode::Recipient),
+            _ => None,
+        }
     }
 }
 
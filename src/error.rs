@@ -29,16 +29,28 @@ pub enum VaultError {
     
     #[error("Unsupported algorithm: {0}")]
     UnsupportedAlgorithm(u8),
+
+    #[error("Unsupported mode: {0}")]
+    UnsupportedMode(u8),
     
     #[error("Password required")]
     PasswordRequired,
+
+    #[error("Passwords do not match")]
+    PasswordMismatch,
     
     #[error("Invalid password or corrupted file")]
     AuthenticationFailed,
     
     #[error("Key derivation failed: {0}")]
     KdfError(String),
-    
+
+    #[error("Keyring error: {0}")]
+    KeyringError(String),
+
+    #[error("Invalid key: {0}")]
+    InvalidKey(String),
+
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
 }
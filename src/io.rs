@@ -0,0 +1,42 @@
+use anyhow::Result;
+use std::path::Path;
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use crate::error::VaultError;
+
+/// Path value that means "use stdin" (as input) or "use stdout" (as output)
+/// instead of a regular file, so the tool can sit in a shell pipeline.
+pub const STDIO_SENTINEL: &str = "-";
+
+/// Does `path` refer to stdin/stdout rather than a regular file?
+pub fn is_stdio(path: &Path) -> bool {
+    path.as_os_str() == STDIO_SENTINEL
+}
+
+/// Open `path` for reading, or stdin if it's the stdio sentinel.
+pub async fn open_input(path: &Path) -> Result<Box<dyn AsyncRead + Unpin + Send>> {
+    if is_stdio(path) {
+        return Ok(Box::new(tokio::io::stdin()));
+    }
+
+    if !path.exists() {
+        return Err(VaultError::InputNotFound(path.to_path_buf()).into());
+    }
+
+    Ok(Box::new(tokio::fs::File::open(path).await?))
+}
+
+/// Open `path` for writing, or stdout if it's the stdio sentinel. The
+/// existence/`--force` check doesn't apply to stdout, since it isn't a
+/// regular file that can already "exist".
+pub async fn create_output(path: &Path, force: bool) -> Result<Box<dyn AsyncWrite + Unpin + Send>> {
+    if is_stdio(path) {
+        return Ok(Box::new(tokio::io::stdout()));
+    }
+
+    if path.exists() && !force {
+        return Err(VaultError::OutputExists(path.to_path_buf()).into());
+    }
+
+    Ok(Box::new(tokio::fs::File::create(path).await?))
+}
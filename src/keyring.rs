@@ -0,0 +1,30 @@
+use anyhow::Result;
+use zeroize::Zeroizing;
+
+use crate::error::VaultError;
+
+/// Service name under which passphrases are stored in the OS secret store
+/// (Secret Service on Linux, Keychain on macOS, Credential Manager on
+/// Windows), with the `--save-key`/`--key` name as the account.
+const SERVICE: &str = "secure-vault";
+
+/// Fetch a passphrase previously stored under `name` with [`save_key`].
+pub fn get_key(name: &str) -> Result<Zeroizing<String>> {
+    let entry =
+        keyring::Entry::new(SERVICE, name).map_err(|e| VaultError::KeyringError(e.to_string()))?;
+    let password = entry
+        .get_password()
+        .map_err(|e| VaultError::KeyringError(e.to_string()))?;
+    Ok(Zeroizing::new(password))
+}
+
+/// Save `password` under `name` in the OS secret store for later retrieval
+/// with [`get_key`].
+pub fn save_key(name: &str, password: &Zeroizing<String>) -> Result<()> {
+    let entry =
+        keyring::Entry::new(SERVICE, name).map_err(|e| VaultError::KeyringError(e.to_string()))?;
+    entry
+        .set_password(password)
+        .map_err(|e| VaultError::KeyringError(e.to_string()))?;
+    Ok(())
+}
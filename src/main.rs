@@ -1,9 +1,12 @@
 use anyhow::Result;
 use clap::Parser;
+use zeroize::Zeroizing;
 
 mod cli;
 mod crypto;
 mod error;
+mod io;
+mod keyring;
 mod utils;
 
 use cli::{Cli, Commands};
@@ -13,9 +16,9 @@ use error::VaultError;
 #[tokio::main]
 async fn main() -> Result<()> {
     env_logger::init();
-    
+
     let cli = Cli::parse();
-    
+
     match cli.command {
         Commands::Encrypt {
             input,
@@ -24,80 +27,154 @@ async fn main() -> Result<()> {
             iterations,
             force,
             verbose,
+            save_key,
+            recipient,
         } => {
             if verbose {
-                println!("🔐 SecureVault - File Encryption");
-                println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-                println!("Input:     {}", input.display());
-                println!("Output:    {}", output.display());
-                println!("Algorithm: {:?}", algorithm);
-                println!("KDF Iterations: {}", iterations);
-                println!();
-            }
-            
-            // Check if output exists
-            if output.exists() && !force {
-                return Err(VaultError::OutputExists(output).into());
+                eprintln!("🔐 SecureVault - File Encryption");
+                eprintln!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+                eprintln!("Input:     {}", input.display());
+                eprintln!("Output:    {}", output.display());
+                eprintln!();
             }
-            
-            // Get password
-            let password = utils::get_password("Enter encryption password: ")?;
-            
-            // Create crypto engine
-            let engine = CryptoEngine::new(algorithm, iterations);
-            
-            // Encrypt file
-            if verbose {
-                println!("⏳ Encrypting...");
+
+            // Open input/output, resolving the stdin/stdout sentinel
+            let mut reader = io::open_input(&input).await?;
+            let mut writer = io::create_output(&output, force).await?;
+
+            if let Some(recipient_hex) = recipient {
+                // Recipient mode: no password involved at all
+                let recipient_public = utils::decode_key_hex(&recipient_hex, "recipient public key")?;
+
+                if verbose {
+                    eprintln!("⏳ Encrypting to recipient public key...");
+                }
+
+                CryptoEngine::encrypt_file_recipient(&mut *reader, &mut *writer, &recipient_public)
+                    .await?;
+            } else {
+                if verbose {
+                    eprintln!("Algorithm: {:?}", algorithm);
+                    eprintln!("KDF Iterations: {}", iterations);
+                    eprintln!();
+                }
+
+                // Get password, confirming it since a typo here would lock
+                // the file forever
+                let password = utils::get_password("Enter encryption password: ", None, true)?;
+
+                if let Some(name) = &save_key {
+                    keyring::save_key(name, &password)?;
+                    if verbose {
+                        eprintln!("🔑 Saved password to OS keyring as '{}'", name);
+                    }
+                }
+
+                let engine = CryptoEngine::new(algorithm, iterations);
+
+                if verbose {
+                    eprintln!("⏳ Encrypting...");
+                }
+
+                engine
+                    .encrypt_file(&mut *reader, &mut *writer, &password)
+                    .await?;
             }
-            
-            engine.encrypt_file(&input, &output, &password).await?;
-            
+
             if verbose {
-                println!("✅ Encryption complete!");
-                println!("📁 Encrypted file: {}", output.display());
+                eprintln!("✅ Encryption complete!");
+                eprintln!("📁 Encrypted file: {}", output.display());
             } else {
-                println!("✅ File encrypted successfully");
+                eprintln!("✅ File encrypted successfully");
             }
         }
-        
+
         Commands::Decrypt {
             input,
             output,
             force,
             verbose,
+            key,
+            identity,
         } => {
             if verbose {
-                println!("🔓 SecureVault - File Decryption");
-                println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-                println!("Input:  {}", input.display());
-                println!("Output: {}", output.display());
-                println!();
-            }
-            
-            // Check if output exists
-            if output.exists() && !force {
-                return Err(VaultError::OutputExists(output).into());
+                eprintln!("🔓 SecureVault - File Decryption");
+                eprintln!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+                eprintln!("Input:  {}", input.display());
+                eprintln!("Output: {}", output.display());
+                eprintln!();
             }
-            
-            // Get password
-            let password = utils::get_password("Enter decryption password: ")?;
-            
+
+            // Open input/output, resolving the stdin/stdout sentinel
+            let mut reader = io::open_input(&input).await?;
+            let mut writer = io::create_output(&output, force).await?;
+
             if verbose {
-                println!("⏳ Decrypting...");
+                eprintln!("⏳ Decrypting...");
             }
-            
-            // Decrypt file
-            CryptoEngine::decrypt_file(&input, &output, &password).await?;
-            
+
+            if let Some(identity_hex) = identity {
+                let identity_secret = utils::decode_key_hex(&identity_hex, "identity secret key")?;
+                CryptoEngine::decrypt_file_recipient(&mut *reader, &mut *writer, &identity_secret)
+                    .await?;
+            } else {
+                let password =
+                    utils::get_password("Enter decryption password: ", key.as_deref(), false)?;
+                CryptoEngine::decrypt_file(&mut *reader, &mut *writer, &password).await?;
+            }
+
             if verbose {
-                println!("✅ Decryption complete!");
-                println!("📁 Decrypted file: {}", output.display());
+                eprintln!("✅ Decryption complete!");
+                eprintln!("📁 Decrypted file: {}", output.display());
             } else {
-                println!("✅ File decrypted successfully");
+                eprintln!("✅ File decrypted successfully");
             }
         }
+
+        Commands::Verify { input, key } => {
+            // Open input, resolving the stdin sentinel
+            let mut reader = io::open_input(&input).await?;
+
+            // Get password
+            let password = utils::get_password("Enter password: ", key.as_deref(), false)?;
+
+            // Walk every chunk through the same authenticated decryption
+            // path, discarding the plaintext instead of writing it out
+            match CryptoEngine::decrypt_file(&mut *reader, &mut tokio::io::sink(), &password).await
+            {
+                Ok(()) => {
+                    eprintln!("✅ File verified: all chunks authenticated successfully");
+                }
+                Err(e) => {
+                    eprintln!("❌ Verification failed: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+
+        Commands::Keygen {
+            secret_out,
+            save_key,
+        } => {
+            let (public_key, secret_key) = crypto::generate_keypair();
+            let secret_key = Zeroizing::new(secret_key);
+
+            if let Some(name) = &save_key {
+                keyring::save_key(name, &Zeroizing::new(hex::encode(*secret_key)))?;
+                eprintln!("🔑 Saved secret key to OS keyring as '{}'", name);
+            } else if let Some(path) = &secret_out {
+                tokio::fs::write(path, hex::encode(*secret_key)).await?;
+                eprintln!("🔑 Wrote secret key to {}", path.display());
+            } else {
+                return Err(VaultError::InvalidKey(
+                    "one of --secret-out or --save-key is required".to_string(),
+                )
+                .into());
+            }
+
+            println!("{}", hex::encode(public_key));
+        }
     }
-    
+
     Ok(())
 }
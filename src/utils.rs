@@ -4,26 +4,64 @@ use zeroize::Zeroizing;
 
 use crate::error::VaultError;
 
-/// Get password from user or environment variable
-pub fn get_password(prompt: &str) -> Result<Zeroizing<String>> {
-    // Try environment variable first (more secure for scripts)
+/// Get the password, trying in order: a saved OS keyring entry (if
+/// `key_name` is given), the `VAULT_PASSWORD` environment variable, then an
+/// interactive prompt. When `confirm` is set and the password came from the
+/// interactive prompt (not the keyring or env var), the user is asked to
+/// re-enter it and an error is returned on mismatch — this is only used for
+/// encryption, so a typo doesn't produce a file nobody can open.
+pub fn get_password(prompt: &str, key_name: Option<&str>, confirm: bool) -> Result<Zeroizing<String>> {
+    // Try the OS keyring first, if the caller asked for a saved key
+    if let Some(name) = key_name {
+        if let Ok(password) = crate::keyring::get_key(name) {
+            return Ok(password);
+        }
+    }
+
+    // Try environment variable next (more secure for scripts)
     if let Ok(password) = std::env::var("VAULT_PASSWORD") {
         return Ok(Zeroizing::new(password));
     }
-    
+
     // Prompt user
     print!("{}", prompt);
     io::stdout().flush()?;
-    
+
     let password = rpassword::read_password()?;
-    
+
     if password.is_empty() {
         return Err(VaultError::PasswordRequired.into());
     }
-    
+
+    if confirm {
+        print!("Confirm password: ");
+        io::stdout().flush()?;
+
+        let confirmation = rpassword::read_password()?;
+        if confirmation != password {
+            return Err(VaultError::PasswordMismatch.into());
+        }
+    }
+
     Ok(Zeroizing::new(password))
 }
 
+/// Decode a hex-encoded 32-byte X25519 key (public or secret), as accepted
+/// by `--recipient`/`--identity` and produced by `keygen`.
+pub fn decode_key_hex(hex_str: &str, what: &str) -> Result<[u8; 32]> {
+    let bytes = hex::decode(hex_str)
+        .map_err(|e| VaultError::InvalidKey(format!("{} is not valid hex: {}", what, e)))?;
+
+    bytes.try_into().map_err(|bytes: Vec<u8>| {
+        VaultError::InvalidKey(format!(
+            "{} must be 32 bytes, got {}",
+            what,
+            bytes.len()
+        ))
+        .into()
+    })
+}
+
 /// Securely compare two byte slices in constant time
 pub fn constant_time_compare(a: &[u8], b: &[u8]) -> bool {
     if a.len() != b.len() {